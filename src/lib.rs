@@ -1,5 +1,7 @@
 use std::{
+    fs, io,
     ops::{Index, IndexMut},
+    path::Path,
     time::Instant,
 };
 
@@ -9,44 +11,106 @@ use nannou::{
     state::mouse::ButtonPosition,
 };
 
+/// Default file a running `Model` saves to and loads from.
+const SAVE_PATH: &str = "save.cac";
+
 pub struct Model<const GRID_SIZE: usize> {
     active: Grid<GRID_SIZE>,
     rules: Vec<Rule>,
+    cell_groups: Vec<Vec<Cell>>,
+    palette: Palette,
     last: Instant,
     paused: bool,
-    fill_state: State,
+    fill_state: Cell,
+    update_mode: UpdateMode,
+    translation: Vec2,
+    zoom: f32,
+    show_lines: bool,
+    drag_origin: Option<Point2>,
+    bpm: f32,
+    undo_stack: Vec<Grid<GRID_SIZE>>,
+    redo_stack: Vec<Grid<GRID_SIZE>>,
+}
+
+/// Caps the undo/redo stacks so experimenting with rules or hand-drawn
+/// states can't grow memory use without bound.
+const MAX_HISTORY: usize = 100;
+
+/// How `update` advances the grid each tick.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum UpdateMode {
+    /// Apply every rule to every cell through a double-buffered sweep, like
+    /// a traditional synchronous automaton.
+    Synchronous,
+    /// Fire a randomly chosen rule at a randomly chosen origin, applying the
+    /// result immediately, `fires_per_tick` times. Avoids the double-buffer
+    /// artifacts synchronous sweeps produce for sand/fluid-style rules.
+    Stochastic { fires_per_tick: usize },
 }
 
 #[derive(Clone)]
 pub struct Grid<const GRID_SIZE: usize> {
-    grid: [[State; GRID_SIZE]; GRID_SIZE],
+    grid: [[Cell; GRID_SIZE]; GRID_SIZE],
 }
 
 impl<const GRID_SIZE: usize> Grid<GRID_SIZE> {
-    fn get_cell(&self, x: usize, y: usize) -> Option<&State> {
+    fn get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
         self.get_col(x).map(|col| col.get(y)).flatten()
     }
 
-    fn get_col(&self, x: usize) -> Option<&[State; GRID_SIZE]> {
+    fn get_col(&self, x: usize) -> Option<&[Cell; GRID_SIZE]> {
         self.grid.get(x)
     }
 
-    fn indexed_iter(&self) -> impl Iterator<Item = (usize, usize, &State)> {
+    /// Converts a signed coordinate into a valid `usize` index, or `None` if
+    /// it falls outside the grid in either direction. A plain `as usize` cast
+    /// wraps negative coordinates instead of rejecting them, so callers whose
+    /// origin may sit off-grid (a rule window overlapping the border, a
+    /// line-of-sight scan) must go through this instead of casting directly.
+    fn checked_index(x: i64, y: i64) -> Option<(usize, usize)> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        (x < GRID_SIZE && y < GRID_SIZE).then_some((x, y))
+    }
+
+    /// The signed-coordinate counterpart to [`Grid::get_cell`], for callers
+    /// stepping outward from a point that may cross off-grid.
+    fn get_cell_signed(&self, x: i64, y: i64) -> Option<&Cell> {
+        let (x, y) = Self::checked_index(x, y)?;
+        self.get_cell(x, y)
+    }
+
+    fn indexed_iter(&self) -> impl Iterator<Item = (usize, usize, &Cell)> {
         self.grid
             .iter()
             .enumerate()
             .flat_map(|(i, col)| col.iter().enumerate().map(move |(j, cell)| (i, j, cell)))
     }
+
+    /// Builds a grid with every cell set to a uniformly random value in
+    /// `0..palette_len`.
+    pub fn fill_random(palette_len: usize) -> Self {
+        let len = palette_len.max(1) as u16;
+        let mut grid = Self::default();
+        for i in 0..GRID_SIZE {
+            for j in 0..GRID_SIZE {
+                grid[(i, j)] = Cell(random_range(0, len));
+            }
+        }
+        grid
+    }
 }
 
-impl<const GRID_SIZE: usize> From<[[State; GRID_SIZE]; GRID_SIZE]> for Grid<GRID_SIZE> {
-    fn from(value: [[State; GRID_SIZE]; GRID_SIZE]) -> Self {
+impl<const GRID_SIZE: usize> From<[[Cell; GRID_SIZE]; GRID_SIZE]> for Grid<GRID_SIZE> {
+    fn from(value: [[Cell; GRID_SIZE]; GRID_SIZE]) -> Self {
         Grid { grid: value }
     }
 }
 
 impl<const GRID_SIZE: usize> Index<(usize, usize)> for Grid<GRID_SIZE> {
-    type Output = State;
+    type Output = Cell;
 
     fn index(&self, index: (usize, usize)) -> &Self::Output {
         &self.grid[index.0][index.1]
@@ -65,56 +129,245 @@ impl<const GRID_SIZE: usize> Default for Grid<GRID_SIZE> {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum State {
-    Full,
-    Empty,
+/// A cell's value, used as an index into a [`Palette`] rather than a fixed
+/// on/off flag. This is what lets a single grid hold more than two states
+/// (ant trails, sand grains, Wireworld wires, ...).
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cell(pub u16);
+
+impl Cell {
+    /// Cycles forward through the palette, wrapping back to index 0.
+    fn next(self, palette_len: usize) -> Self {
+        let len = palette_len.max(1);
+        Cell(((self.0 as usize + 1) % len) as u16)
+    }
+
+    /// Cycles backward through the palette, wrapping to the last index.
+    fn prev(self, palette_len: usize) -> Self {
+        let len = palette_len.max(1);
+        Cell(((self.0 as usize + len - 1) % len) as u16)
+    }
 }
 
-impl State {
-    fn color(&self) -> Rgb<Srgb, u8> {
-        match self {
-            State::Full => WHITE,
-            State::Empty => BLACK,
-        }
+impl std::fmt::Debug for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Maps each [`Cell`] value to the color it's drawn with. Indices past the
+/// end of the palette fall back to black.
+#[derive(Clone)]
+pub struct Palette(Vec<Rgb<Srgb, u8>>);
+
+impl Palette {
+    pub fn new(colors: Vec<Rgb<Srgb, u8>>) -> Self {
+        Palette(colors)
     }
 
-    fn next(self) -> Self {
-        use State::*;
-        match self {
-            Full => Empty,
-            Empty => Full,
-        }
+    fn color(&self, cell: Cell) -> Rgb<Srgb, u8> {
+        self.0.get(cell.0 as usize).copied().unwrap_or(BLACK)
     }
 
-    fn prev(self) -> Self {
-        use State::*;
-        match self {
-            Full => Empty,
-            Empty => Full,
-        }
+    fn len(&self) -> usize {
+        self.0.len()
     }
 }
 
-impl std::fmt::Debug for State {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            State::Full => write!(f, "X"),
-            State::Empty => write!(f, "O"),
-        }
+impl Default for Palette {
+    fn default() -> Self {
+        Palette(vec![BLACK, WHITE])
     }
 }
 
 pub enum Rule {
     Linear {
-        in_state: Vec<Vec<Option<State>>>,
-        out_state: Vec<Vec<Option<State>>>,
+        in_state: Vec<Vec<RuleCellFrom>>,
+        out_state: Vec<Vec<RuleCellTo>>,
+        symmetry: Symmetry,
     },
     Radial {
-        current_state: State,
-        surroundings: Vec<(State, Comparison<usize>)>,
-        final_state: State,
+        current_state: Cell,
+        surroundings: Vec<(Cell, Comparison<usize>)>,
+        final_state: Cell,
     },
+    Directional {
+        current_state: Cell,
+        directions: Vec<(Offset, Cell)>,
+        /// Checked against the number of `directions` whose first-seen cell
+        /// equals that direction's required value, e.g.
+        /// `GreaterThanOrEqual(5)` for "can see 5 or more of them".
+        threshold: Comparison<usize>,
+        max_range: Option<usize>,
+        final_state: Cell,
+    },
+}
+
+/// A single step direction on the grid, e.g. one of the 8 compass vectors
+/// used to scan a line of sight out from a cell.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Offset(pub i64, pub i64);
+
+impl Rule {
+    /// Expands a `Linear` rule's `symmetry` into its rotated/mirrored
+    /// variants, each its own `Symmetry::None` rule so `linear` can just try
+    /// them in turn. Other rule kinds pass through unchanged.
+    fn expand_symmetry(self) -> Vec<Rule> {
+        match self {
+            Rule::Linear {
+                in_state,
+                out_state,
+                symmetry,
+            } => symmetry
+                .variants_of(in_state, out_state)
+                .into_iter()
+                .map(|(in_state, out_state)| Rule::Linear {
+                    in_state,
+                    out_state,
+                    symmetry: Symmetry::None,
+                })
+                .collect(),
+            radial => vec![radial],
+        }
+    }
+}
+
+/// A single position in a [`Rule::Linear`] input window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleCellFrom {
+    /// Matches any cell, regardless of its value (including the
+    /// default/empty `Cell(0)`), and also positions that fall off the grid.
+    Any,
+    /// Matches only the given cell value.
+    One(Cell),
+    /// Matches any cell value belonging to `cell_groups[_]` on the [`Model`].
+    Group(usize),
+}
+
+/// A single position in a [`Rule::Linear`] output window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleCellTo {
+    /// Leaves this position untouched.
+    None,
+    /// Writes the given cell value.
+    One(Cell),
+    /// Writes a random member of `cell_groups[_]` on the [`Model`].
+    GroupRandom(usize),
+    /// Writes back whatever value was read from the `_`-th position of the
+    /// matched input window (in the same order `in_state` is laid out).
+    Copy(usize),
+}
+
+/// Automatic rotation/reflection variants for a [`Rule::Linear`] pattern, so
+/// one authored window fires in every orientation instead of needing a
+/// hand-written rule per direction.
+#[derive(Clone, Copy)]
+pub enum Symmetry {
+    /// Use the pattern exactly as authored.
+    None,
+    /// Also try the pattern rotated 90°, 180°, and 270°.
+    Rotate4,
+    /// The four `Rotate4` orientations, each also tried mirrored.
+    Rotate4Mirror8,
+}
+
+impl Symmetry {
+    fn variants_of(
+        self,
+        in_state: Vec<Vec<RuleCellFrom>>,
+        out_state: Vec<Vec<RuleCellTo>>,
+    ) -> Vec<(Vec<Vec<RuleCellFrom>>, Vec<Vec<RuleCellTo>>)> {
+        let rotations = match self {
+            Symmetry::None => vec![(in_state, out_state)],
+            Symmetry::Rotate4 | Symmetry::Rotate4Mirror8 => {
+                let mut variants = vec![(in_state, out_state)];
+                for _ in 0..3 {
+                    let (last_in, last_out) = variants.last().unwrap();
+                    variants.push(rotate_pattern(last_in, last_out));
+                }
+                variants
+            }
+        };
+
+        if let Symmetry::Rotate4Mirror8 = self {
+            let mirrored: Vec<_> = rotations
+                .iter()
+                .map(|(in_state, out_state)| mirror_pattern(in_state, out_state))
+                .collect();
+            rotations.into_iter().chain(mirrored).collect()
+        } else {
+            rotations
+        }
+    }
+}
+
+fn pattern_dims<T>(grid: &[Vec<T>]) -> (usize, usize) {
+    (grid.len(), grid.first().map_or(0, Vec::len))
+}
+
+// relocates every `(in_state, out_state)` cell from `(ri, rj)` to
+// `coord(ri, rj)`, remapping `RuleCellTo::Copy` indices along the way so they
+// still point at the same (now relocated) input cell
+fn apply_transform(
+    in_state: &[Vec<RuleCellFrom>],
+    out_state: &[Vec<RuleCellTo>],
+    new_dims: (usize, usize),
+    coord: impl Fn(usize, usize) -> (usize, usize),
+) -> (Vec<Vec<RuleCellFrom>>, Vec<Vec<RuleCellTo>>) {
+    let (rows, cols) = pattern_dims(in_state);
+    let (new_rows, new_cols) = new_dims;
+
+    let mut index_map = vec![0usize; rows * cols];
+    for ri in 0..rows {
+        for rj in 0..cols {
+            let (new_ri, new_rj) = coord(ri, rj);
+            index_map[ri * cols + rj] = new_ri * new_cols + new_rj;
+        }
+    }
+
+    let mut new_in: Vec<Vec<Option<RuleCellFrom>>> = vec![vec![None; new_cols]; new_rows];
+    let mut new_out: Vec<Vec<Option<RuleCellTo>>> = vec![vec![None; new_cols]; new_rows];
+    for ri in 0..rows {
+        for rj in 0..cols {
+            let (new_ri, new_rj) = coord(ri, rj);
+            new_in[new_ri][new_rj] = Some(in_state[ri][rj]);
+            new_out[new_ri][new_rj] = Some(match out_state[ri][rj] {
+                RuleCellTo::Copy(k) => RuleCellTo::Copy(index_map[k]),
+                other => other,
+            });
+        }
+    }
+
+    (
+        new_in
+            .into_iter()
+            .map(|row| row.into_iter().map(|cell| cell.unwrap()).collect())
+            .collect(),
+        new_out
+            .into_iter()
+            .map(|row| row.into_iter().map(|cell| cell.unwrap()).collect())
+            .collect(),
+    )
+}
+
+fn rotate_pattern(
+    in_state: &[Vec<RuleCellFrom>],
+    out_state: &[Vec<RuleCellTo>],
+) -> (Vec<Vec<RuleCellFrom>>, Vec<Vec<RuleCellTo>>) {
+    let (rows, cols) = pattern_dims(in_state);
+    apply_transform(in_state, out_state, (cols, rows), |ri, rj| {
+        (cols - 1 - rj, ri)
+    })
+}
+
+fn mirror_pattern(
+    in_state: &[Vec<RuleCellFrom>],
+    out_state: &[Vec<RuleCellTo>],
+) -> (Vec<Vec<RuleCellFrom>>, Vec<Vec<RuleCellTo>>) {
+    let (rows, cols) = pattern_dims(in_state);
+    apply_transform(in_state, out_state, (rows, cols), |ri, rj| {
+        (rows - 1 - ri, rj)
+    })
 }
 
 pub enum Comparison<T> {
@@ -143,27 +396,129 @@ impl<T: PartialOrd> Comparison<T> {
     }
 }
 
-impl Default for State {
-    fn default() -> Self {
-        Self::Empty
-    }
-}
-
 impl<const GRID_SIZE: usize> Model<GRID_SIZE> {
     pub fn model(
         _app: &App,
         starting_state: Option<Grid<GRID_SIZE>>,
         rules: Vec<Rule>,
+        cell_groups: Vec<Vec<Cell>>,
+        palette: Palette,
         paused: bool,
+        update_mode: UpdateMode,
+        bpm: f32,
     ) -> Model<GRID_SIZE> {
         let active = starting_state.unwrap_or_default();
+        let rules = rules.into_iter().flat_map(Rule::expand_symmetry).collect();
         Model {
             active,
             rules,
+            cell_groups,
+            palette,
             last: Instant::now(),
             paused,
             fill_state: Default::default(),
+            update_mode,
+            translation: Vec2::ZERO,
+            zoom: 1.,
+            show_lines: true,
+            drag_origin: None,
+            bpm,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+
+    /// Records the current grid so [`Model::undo`] can return to it later,
+    /// discarding the oldest entry once [`MAX_HISTORY`] is exceeded. Starting
+    /// a new branch of history invalidates any pending redo.
+    fn snapshot(&mut self) {
+        self.undo_stack.push(self.active.clone());
+        if self.undo_stack.len() > MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    /// Restores the grid from just before the last snapshot, if any.
+    fn undo(&mut self) {
+        if let Some(previous) = self.undo_stack.pop() {
+            self.redo_stack.push(std::mem::replace(&mut self.active, previous));
+        }
+    }
+
+    /// Reapplies the grid undone by the most recent [`Model::undo`], if any.
+    fn redo(&mut self) {
+        if let Some(next) = self.redo_stack.pop() {
+            self.undo_stack.push(std::mem::replace(&mut self.active, next));
+        }
+    }
+
+    /// Milliseconds between ticks implied by `bpm` (one tick per beat).
+    fn tick_interval_ms(&self) -> u128 {
+        (60_000. / self.bpm.max(1.)) as u128
+    }
+
+    /// Writes the active grid and fill state to `path` as a compact
+    /// run-length-encoded text format: a `fill <cell>` header line followed
+    /// by one `<cell>:<count>` run per line, in the grid's column-major
+    /// iteration order. Rule definitions aren't persisted, since they're
+    /// supplied by the caller rather than edited at runtime.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = format!("fill {}\n", self.fill_state.0);
+        let mut run: Option<(u16, usize)> = None;
+        for (_, _, cell) in self.active.indexed_iter() {
+            match &mut run {
+                Some((value, count)) if *value == cell.0 => *count += 1,
+                _ => {
+                    if let Some((value, count)) = run.replace((cell.0, 1)) {
+                        out.push_str(&format!("{value}:{count}\n"));
+                    }
+                }
+            }
         }
+        if let Some((value, count)) = run {
+            out.push_str(&format!("{value}:{count}\n"));
+        }
+        fs::write(path, out)
+    }
+
+    /// Restores the active grid and fill state previously written by
+    /// [`Model::save`].
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+        let fill = lines
+            .next()
+            .and_then(|line| line.strip_prefix("fill "))
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| invalid("missing or malformed fill header"))?;
+
+        let mut values = Vec::with_capacity(GRID_SIZE * GRID_SIZE);
+        for line in lines {
+            let (value, count) = line
+                .split_once(':')
+                .ok_or_else(|| invalid("malformed run"))?;
+            let value: u16 = value.parse().map_err(|_| invalid("malformed run value"))?;
+            let count: usize = count.parse().map_err(|_| invalid("malformed run count"))?;
+            values.extend(std::iter::repeat(Cell(value)).take(count));
+        }
+        if values.len() != GRID_SIZE * GRID_SIZE {
+            return Err(invalid("saved grid size doesn't match GRID_SIZE"));
+        }
+
+        let mut values = values.into_iter();
+        let mut grid = Grid::default();
+        for i in 0..GRID_SIZE {
+            for j in 0..GRID_SIZE {
+                grid[(i, j)] = values.next().unwrap();
+            }
+        }
+
+        self.active = grid;
+        self.fill_state = Cell(fill);
+        Ok(())
     }
 }
 
@@ -187,25 +542,62 @@ pub fn event<const GRID_SIZE: usize>(app: &App, model: &mut Model<GRID_SIZE>, ev
             KeyPressed(key) => {
                 match key {
                     Key::P => model.paused = !model.paused,
-                    Key::C => model.active = Default::default(),
+                    Key::C => {
+                        model.snapshot();
+                        model.active = Default::default();
+                    }
+                    Key::G => model.show_lines = !model.show_lines,
+                    Key::M => {
+                        model.update_mode = match model.update_mode {
+                            UpdateMode::Synchronous => {
+                                UpdateMode::Stochastic { fires_per_tick: 1 }
+                            }
+                            UpdateMode::Stochastic { .. } => UpdateMode::Synchronous,
+                        }
+                    }
+                    Key::Equals => model.bpm += 10.,
+                    Key::Minus => model.bpm = (model.bpm - 10.).max(1.),
+                    Key::S => {
+                        let _ = model.save(SAVE_PATH);
+                    }
+                    Key::L => {
+                        model.snapshot();
+                        let _ = model.load(SAVE_PATH);
+                    }
+                    Key::R => {
+                        model.snapshot();
+                        model.active = Grid::fill_random(model.palette.len());
+                    }
+                    Key::Z => model.undo(),
+                    Key::Y => model.redo(),
                     _ => (),
                 }
             }
+            // holding the right mouse button and dragging pans the viewport
+            MouseMoved(pos) => {
+                if let ButtonPosition::Down(_) = app.mouse.buttons.right() {
+                    if let Some(origin) = model.drag_origin {
+                        model.translation += pos - origin;
+                    }
+                    model.drag_origin = Some(pos);
+                } else {
+                    model.drag_origin = None;
+                }
+            }
+            // scrolling normally cycles the fill state; holding shift zooms
+            // the viewport instead
             MouseWheel(delta, phase) => {
-                match phase {
-                    TouchPhase::Moved => match delta {
-                        MouseScrollDelta::LineDelta(_, y) => if y > 0. {
-                            model.fill_state = model.fill_state.next();
-                        } else if y < 0. {
-                            model.fill_state = model.fill_state.prev();
-                        },
-                        MouseScrollDelta::PixelDelta(pos) => if pos.y > 0. {
-                            model.fill_state = model.fill_state.next();
-                        } else if pos.y < 0. {
-                            model.fill_state = model.fill_state.prev();
-                        },
-                    },
-                    _ => (),
+                let scroll = match (phase, delta) {
+                    (TouchPhase::Moved, MouseScrollDelta::LineDelta(_, y)) => y,
+                    (TouchPhase::Moved, MouseScrollDelta::PixelDelta(pos)) => pos.y as f32,
+                    _ => 0.,
+                };
+                if app.keys.mods.shift() {
+                    model.zoom = (model.zoom * (1. + scroll * 0.1)).max(0.1);
+                } else if scroll > 0. {
+                    model.fill_state = model.fill_state.next(model.palette.len());
+                } else if scroll < 0. {
+                    model.fill_state = model.fill_state.prev(model.palette.len());
                 }
             }
             _ => (),
@@ -214,59 +606,169 @@ pub fn event<const GRID_SIZE: usize>(app: &App, model: &mut Model<GRID_SIZE>, ev
     }
 }
 
-// changes the state of the cell that was interacted with
+// cell size and the on-screen position of cell (0, 0)'s center, after
+// applying the viewport's pan/zoom; shared by `draw_grid` and `update_grid`
+// so the two stay in lockstep
+fn grid_geometry<const GRID_SIZE: usize>(win: &Rect, model: &Model<GRID_SIZE>) -> (f32, f32, f32, f32) {
+    let w = (win.x.len() / (GRID_SIZE as f32)) * model.zoom;
+    let h = (win.y.len() / (GRID_SIZE as f32)) * model.zoom;
+    let x0 = win.x.start + w / 2. + model.translation.x;
+    let y0 = win.y.end - h / 2. + model.translation.y;
+    (w, h, x0, y0)
+}
+
+// changes the state of the cell that was interacted with. `event` re-fires
+// this on every frame the mouse button stays down, so snapshotting
+// unconditionally would flood undo history with near-duplicate frames of the
+// same click or drag stroke — only record history when the cell actually
+// changes, giving undo per-edit granularity instead of per-frame
 fn update_grid<const GRID_SIZE: usize>(win: &Rect, model: &mut Model<GRID_SIZE>, pos: &Point2) {
-    let w = win.x.len() / (GRID_SIZE as f32);
-    let h = win.y.len() / (GRID_SIZE as f32);
-    let x = ((pos.x - win.x.start) / w) as usize;
-    let y = ((win.y.end - pos.y) / h) as usize;
-    model.active[(x.min(GRID_SIZE - 1).max(0), y.min(GRID_SIZE - 1).max(0))] =
-        model.fill_state.clone();
+    let (w, h, x0, y0) = grid_geometry(win, model);
+    let x = (((pos.x - x0) / w) + 0.5) as usize;
+    let y = ((y0 - pos.y) / h + 0.5) as usize;
+    let index = (x.min(GRID_SIZE - 1).max(0), y.min(GRID_SIZE - 1).max(0));
+    if model.active[index] == model.fill_state {
+        return;
+    }
+    model.snapshot();
+    model.active[index] = model.fill_state.clone();
 }
 
 pub fn update<const GRID_SIZE: usize>(_app: &App, model: &mut Model<GRID_SIZE>, _update: Update) {
-    if model.paused || model.last.elapsed().as_millis() < 50 {
+    if model.paused || model.last.elapsed().as_millis() < model.tick_interval_ms() {
         return;
     } else {
         model.last = Instant::now();
     }
-    let mut inactive = model.active.clone();
-    for rule in model.rules.iter() {
-        for (i, j, cell) in model.active.indexed_iter() {
-            if match rule {
+    model.snapshot();
+    match model.update_mode {
+        UpdateMode::Synchronous => model.update_synchronous(),
+        UpdateMode::Stochastic { fires_per_tick } => model.update_stochastic(fires_per_tick),
+    }
+}
+
+impl<const GRID_SIZE: usize> Model<GRID_SIZE> {
+    fn update_synchronous(&mut self) {
+        let mut inactive = self.active.clone();
+        for rule in self.rules.iter() {
+            for (i, j, cell) in self.active.indexed_iter() {
+                if match rule {
+                    Rule::Linear {
+                        in_state,
+                        out_state,
+                        ..
+                    } => self.linear((i as i64, j as i64), &mut inactive, in_state, out_state),
+                    Rule::Radial {
+                        current_state,
+                        surroundings,
+                        final_state,
+                    } => self.radial(
+                        &cell,
+                        (i, j),
+                        &mut inactive,
+                        current_state,
+                        surroundings,
+                        final_state,
+                    ),
+                    Rule::Directional {
+                        current_state,
+                        directions,
+                        threshold,
+                        max_range,
+                        final_state,
+                    } => self.directional(
+                        &cell,
+                        (i, j),
+                        &mut inactive,
+                        current_state,
+                        directions,
+                        threshold,
+                        *max_range,
+                        final_state,
+                    ),
+                } {
+                    continue;
+                }
+            }
+        }
+        self.active = inactive;
+    }
+
+    fn update_stochastic(&mut self, fires_per_tick: usize) {
+        if self.rules.is_empty() {
+            return;
+        }
+        // extend the origin search below 0 so rule windows that only
+        // partially overlap the top/left grid border are still reachable,
+        // per snad's border-search fix. The origin itself may
+        // land off-grid, so it's kept signed until each rule kind decides
+        // how to treat that (`linear` bounds-checks per pattern cell;
+        // `radial`/`directional` require the origin cell itself to exist).
+        let pad = GRID_SIZE as i64 - 1;
+        for _ in 0..fires_per_tick {
+            let rule = &self.rules[random_range(0, self.rules.len())];
+            let x = random_range(-pad, GRID_SIZE as i64);
+            let y = random_range(-pad, GRID_SIZE as i64);
+            let mut next = self.active.clone();
+            let applied = match rule {
                 Rule::Linear {
                     in_state,
                     out_state,
-                } => model.linear((i, j), &mut inactive, in_state, out_state),
+                    ..
+                } => self.linear((x, y), &mut next, in_state, out_state),
                 Rule::Radial {
                     current_state,
                     surroundings,
                     final_state,
-                } => model.radial(
-                    &cell,
-                    (i, j),
-                    &mut inactive,
+                } => Grid::<GRID_SIZE>::checked_index(x, y).is_some_and(|cell_cords| {
+                    self.active.get_cell(cell_cords.0, cell_cords.1).is_some_and(|cell| {
+                        self.radial(
+                            cell,
+                            cell_cords,
+                            &mut next,
+                            current_state,
+                            surroundings,
+                            final_state,
+                        )
+                    })
+                }),
+                Rule::Directional {
                     current_state,
-                    surroundings,
+                    directions,
+                    threshold,
+                    max_range,
                     final_state,
-                ),
-            } {
-                continue;
+                } => Grid::<GRID_SIZE>::checked_index(x, y).is_some_and(|cell_cords| {
+                    self.active.get_cell(cell_cords.0, cell_cords.1).is_some_and(|cell| {
+                        self.directional(
+                            cell,
+                            cell_cords,
+                            &mut next,
+                            current_state,
+                            directions,
+                            threshold,
+                            *max_range,
+                            final_state,
+                        )
+                    })
+                }),
+            };
+            if applied {
+                self.active = next;
             }
         }
     }
-    model.active = inactive;
 }
 
 impl<const GRID_SIZE: usize> Model<GRID_SIZE> {
     fn radial(
         &self,
-        cell: &State,
+        cell: &Cell,
         cell_cords: (usize, usize),
         inactive: &mut Grid<GRID_SIZE>,
-        current_state: &State,
-        surroundings: &[(State, Comparison<usize>)],
-        final_state: &State,
+        current_state: &Cell,
+        surroundings: &[(Cell, Comparison<usize>)],
+        final_state: &Cell,
     ) -> bool {
         if current_state == cell {
             let cells: Vec<_> = (-1i64..=1)
@@ -293,15 +795,91 @@ impl<const GRID_SIZE: usize> Model<GRID_SIZE> {
         return false;
     }
 
-    fn linear(
+    // steps outward from `cell_cords` along `offset`, skipping transparent
+    // (default-valued) cells, until it finds an opaque one, runs out of
+    // `max_range`, or falls off the edge of the grid
+    fn sight(
         &self,
         cell_cords: (usize, usize),
+        offset: Offset,
+        max_range: Option<usize>,
+    ) -> Option<Cell> {
+        let (mut x, mut y) = (cell_cords.0 as i64, cell_cords.1 as i64);
+        let mut steps = 0;
+        loop {
+            if max_range.is_some_and(|limit| steps >= limit) {
+                return None;
+            }
+            x += offset.0;
+            y += offset.1;
+            steps += 1;
+            let cell = *self.active.get_cell_signed(x, y)?;
+            if cell != Cell::default() {
+                return Some(cell);
+            }
+        }
+    }
+
+    fn directional(
+        &self,
+        cell: &Cell,
+        cell_cords: (usize, usize),
+        inactive: &mut Grid<GRID_SIZE>,
+        current_state: &Cell,
+        directions: &[(Offset, Cell)],
+        threshold: &Comparison<usize>,
+        max_range: Option<usize>,
+        final_state: &Cell,
+    ) -> bool {
+        if current_state != cell {
+            return false;
+        }
+        // count how many directions' first-seen cell matches that
+        // direction's own required value, e.g. "5 of the 8 compass
+        // directions see a full cell"
+        let count = directions
+            .iter()
+            .filter(|(offset, required)| {
+                self.sight(cell_cords, *offset, max_range) == Some(*required)
+            })
+            .count();
+
+        if threshold.compare(count) {
+            inactive[(cell_cords.0, cell_cords.1)] = final_state.clone();
+            return true;
+        }
+        false
+    }
+
+    // a rule-pattern cell matches a grid cell if it's a wildcard, an exact
+    // value, or a member of the referenced cell group
+    fn matches_pattern(&self, pattern: &RuleCellFrom, cell: Option<&Cell>) -> bool {
+        match (pattern, cell) {
+            (RuleCellFrom::Any, _) => true,
+            (RuleCellFrom::One(expected), Some(actual)) => actual == expected,
+            (RuleCellFrom::Group(group), Some(actual)) => self
+                .cell_groups
+                .get(*group)
+                .is_some_and(|members| members.contains(actual)),
+            (_, None) => false,
+        }
+    }
+
+    // `cell_cords` is signed because a stochastically chosen origin may sit
+    // off-grid while the tail of its pattern still overlaps the border; each
+    // position is bounds-checked independently rather than assuming the
+    // whole window is in range
+    fn linear(
+        &self,
+        cell_cords: (i64, i64),
         inactive: &mut Grid<GRID_SIZE>,
-        in_state: &[Vec<Option<State>>],
-        out_state: &[Vec<Option<State>>],
+        in_state: &[Vec<RuleCellFrom>],
+        out_state: &[Vec<RuleCellTo>],
     ) -> bool {
-        // check all the states relative to the given cell
-        if in_state
+        // check all the states relative to the given cell, recording what was
+        // actually read at each position so `RuleCellTo::Copy` can reuse it
+        let mut matched = Vec::new();
+        let all_match = in_state
             .iter()
             .enumerate()
             .flat_map(|(ri, rule_col)| {
@@ -310,28 +888,51 @@ impl<const GRID_SIZE: usize> Model<GRID_SIZE> {
                     .enumerate()
                     .map(move |(rj, rule_cell)| (ri, rj, rule_cell))
             })
-            .filter_map(|(ri, rj, rule_cell)| rule_cell.map(|cell| (ri, rj, cell)))
             .all(|(ri, rj, rule_cell)| {
-                self.active
-                    .get_cell(cell_cords.0 + ri, cell_cords.1 + rj)
-                    .is_some_and(|cell| cell == &rule_cell)
-                    && inactive
-                        .get_cell(cell_cords.0 + ri, cell_cords.1 + rj)
-                        .is_some_and(|cell| cell == &rule_cell)
-            })
-        {
-            // if cells match expected, perform the swaps to the new layout
-            for (ri, rj, state) in out_state.iter().enumerate().flat_map(|(ri, out_col)| {
-                out_col
-                    .iter()
-                    .enumerate()
-                    .filter_map(move |(rj, state)| state.map(|state| (ri, rj, state)))
-            }) {
-                inactive[(cell_cords.0 + ri, cell_cords.1 + rj)] = state.clone();
+                let (x, y) = (cell_cords.0 + ri as i64, cell_cords.1 + rj as i64);
+                let active_cell = self.active.get_cell_signed(x, y);
+                let inactive_cell = inactive.get_cell_signed(x, y);
+                matched.push(active_cell.copied());
+                self.matches_pattern(rule_cell, active_cell)
+                    && self.matches_pattern(rule_cell, inactive_cell)
+            });
+
+        if !all_match {
+            return false;
+        }
+
+        // if cells match expected, perform the swaps to the new layout
+        for (ri, rj, rule_cell) in out_state.iter().enumerate().flat_map(|(ri, out_col)| {
+            out_col
+                .iter()
+                .enumerate()
+                .map(move |(rj, rule_cell)| (ri, rj, rule_cell))
+        }) {
+            let state = match rule_cell {
+                RuleCellTo::None => continue,
+                RuleCellTo::One(state) => *state,
+                RuleCellTo::Copy(k) => match matched.get(*k).copied().flatten() {
+                    Some(state) => state,
+                    None => continue,
+                },
+                RuleCellTo::GroupRandom(group) => match self
+                    .cell_groups
+                    .get(*group)
+                    .filter(|members| !members.is_empty())
+                {
+                    Some(members) => members[random_range(0, members.len())],
+                    None => continue,
+                },
+            };
+            // a pattern matching the in-bounds tail of an off-grid window can
+            // still ask to write an out-of-bounds position; just skip it
+            if let Some(index) =
+                Grid::<GRID_SIZE>::checked_index(cell_cords.0 + ri as i64, cell_cords.1 + rj as i64)
+            {
+                inactive[index] = state;
             }
-            return true;
         }
-        return false;
+        true
     }
 }
 
@@ -347,22 +948,124 @@ pub fn view<const GRID_SIZE: usize>(app: &App, model: &Model<GRID_SIZE>, frame:
 }
 
 fn draw_grid<const GRID_SIZE: usize>(draw: &Draw, win: &Rect, model: &Model<GRID_SIZE>) {
-    let w = win.x.len() / (GRID_SIZE as f32);
-    let h = win.y.len() / (GRID_SIZE as f32);
-    let x0 = win.x.start + w / 2.;
-    let y0 = win.y.end - h / 2.;
+    let (w, h, x0, y0) = grid_geometry(win, model);
     for (i, j, cell) in model.active.indexed_iter() {
-        draw.rect()
-            .x_y(x0 + (i as f32) * w, y0 - (j as f32) * h)
-            .w_h(w, h)
-            .stroke_weight(0.5)
-            .stroke(GRAY)
-            .color(cell.color());
+        let (x, y) = (x0 + (i as f32) * w, y0 - (j as f32) * h);
+        // skip cells that have been panned/zoomed off screen
+        if x + w / 2. < win.x.start
+            || x - w / 2. > win.x.end
+            || y + h / 2. < win.y.start
+            || y - h / 2. > win.y.end
+        {
+            continue;
+        }
+        let rect = draw.rect().x_y(x, y).w_h(w, h).color(model.palette.color(*cell));
+        if model.show_lines {
+            rect.stroke_weight(0.5).stroke(GRAY);
+        }
     }
+    // the fill indicator is a fixed UI element, not part of the panned/zoomed
+    // grid, so it's sized and positioned straight from `win` rather than
+    // through `grid_geometry`
+    let swatch_w = win.x.len() / (GRID_SIZE as f32);
+    let swatch_h = win.y.len() / (GRID_SIZE as f32);
+    let swatch_x0 = win.x.start + swatch_w / 2.;
+    let swatch_y0 = win.y.end - swatch_h / 2.;
     draw.rect()
-        .x_y(x0 + w / 2., y0 - h / 2.)
-        .w_h(w.min(h), w.min(h))
+        .x_y(swatch_x0 + swatch_w / 2., swatch_y0 - swatch_h / 2.)
+        .w_h(swatch_w.min(swatch_h), swatch_w.min(swatch_h))
         .stroke_weight(0.5)
         .stroke(GRAY)
-        .color(model.fill_state.color());
+        .color(model.palette.color(model.fill_state));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an asymmetric L-shaped window so a rotation bug can't hide behind
+    // accidental symmetry: distinct wildcard/value/group cells, and a
+    // `Copy` that must keep pointing at the same logical input cell through
+    // every step of the rotation
+    fn l_shaped_pattern() -> (Vec<Vec<RuleCellFrom>>, Vec<Vec<RuleCellTo>>) {
+        let in_state = vec![
+            vec![RuleCellFrom::Any, RuleCellFrom::One(Cell(1))],
+            vec![RuleCellFrom::Group(0), RuleCellFrom::One(Cell(2))],
+        ];
+        let out_state = vec![
+            vec![RuleCellTo::Copy(3), RuleCellTo::None],
+            vec![RuleCellTo::None, RuleCellTo::Copy(0)],
+        ];
+        (in_state, out_state)
+    }
+
+    #[test]
+    fn rotate_pattern_four_times_returns_to_original() {
+        let (in_state, out_state) = l_shaped_pattern();
+        let mut pattern = (in_state.clone(), out_state.clone());
+        for _ in 0..4 {
+            pattern = rotate_pattern(&pattern.0, &pattern.1);
+        }
+        assert_eq!(pattern, (in_state, out_state));
+    }
+
+    #[test]
+    fn mirror_pattern_is_its_own_inverse() {
+        let (in_state, out_state) = l_shaped_pattern();
+        let once = mirror_pattern(&in_state, &out_state);
+        let twice = mirror_pattern(&once.0, &once.1);
+        assert_eq!(twice, (in_state, out_state));
+    }
+
+    #[test]
+    fn rotate_pattern_remaps_copy_indices_to_the_relocated_cell() {
+        // position (0, 0) holds `Any` (flat index 0) and is read back by the
+        // `Copy(0)` at (1, 1); rotation relocates (0, 0) to (1, 0), whose
+        // flat index is 2, so whichever output cell used to say `Copy(0)`
+        // must now say `Copy(2)` to keep reading the same logical cell
+        let (in_state, out_state) = l_shaped_pattern();
+        let (rotated_in, rotated_out) = rotate_pattern(&in_state, &out_state);
+        assert!(matches!(rotated_in[1][0], RuleCellFrom::Any));
+        assert_eq!(rotated_out[0][1], RuleCellTo::Copy(2));
+    }
+
+    fn test_model() -> Model<3> {
+        Model {
+            active: Grid::default(),
+            rules: Vec::new(),
+            cell_groups: Vec::new(),
+            palette: Palette::default(),
+            last: Instant::now(),
+            paused: true,
+            fill_state: Cell(0),
+            update_mode: UpdateMode::Synchronous,
+            translation: Vec2::ZERO,
+            zoom: 1.,
+            show_lines: true,
+            drag_origin: None,
+            bpm: 60.,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn save_then_load_round_trips_grid_and_fill_state() {
+        let mut model = test_model();
+        model.active[(0, 0)] = Cell(2);
+        model.active[(1, 2)] = Cell(5);
+        model.fill_state = Cell(1);
+
+        let path = std::env::temp_dir().join("cellular_automata_save_load_test.cac");
+        model.save(&path).expect("save should succeed");
+
+        let mut loaded = test_model();
+        loaded.load(&path).expect("load should succeed");
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.fill_state, model.fill_state);
+        for (i, j, cell) in model.active.indexed_iter() {
+            assert_eq!(loaded.active.get_cell(i, j), Some(cell));
+        }
+    }
 }